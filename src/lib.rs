@@ -1,5 +1,13 @@
 //! `sntp_request` Tiny Rust library to request timestamp from [NTP servers](http://www.ntp.org) trough [SNTP protocol](https://tools.ietf.org/html/rfc4330).
 //!
+//! By default this crate pulls in `std` and exposes [`SntpRequest`], a
+//! convenience client built on [`std::net::UdpSocket`]. The protocol itself
+//! is implemented against the [`NtpUdpSocket`] and [`NtpTimestampGenerator`]
+//! traits, so disabling the default `std` feature makes the crate
+//! `#![no_std]` and usable on embedded targets: implement those two traits
+//! for your platform's UDP stack and clock, bundle them in a [`NtpContext`],
+//! and drive the exchange from there.
+//!
 //! # Example
 //!
 //! The example below shows how to obtain precise timestamp from main NTP server:
@@ -18,12 +26,29 @@
 //! }
 //! ```
 
-use std::cell::Cell;
-use std::convert::TryInto;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+extern crate std;
+
+use core::cell::Cell;
+use core::convert::TryInto;
+use core::mem;
+use core::time::Duration;
+
+#[cfg(feature = "std")]
 use std::io::{self, Error, ErrorKind};
-use std::mem;
+#[cfg(feature = "std")]
 use std::net::{ToSocketAddrs, UdpSocket};
-use std::time::Duration;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+#[cfg(feature = "std")]
+use std::thread;
+#[cfg(feature = "std")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "std")]
+use rand::RngCore;
 
 const SNTP_TIME_OFFSET: u32 = 2_208_988_800;
 
@@ -36,102 +61,354 @@ fn read_be_u32(input: &mut &[u8]) -> u32 {
     u32::from_be_bytes(int_bytes.try_into().unwrap())
 }
 
+/// Converts a 32.32 fixed-point NTP timestamp into seconds as a `f64`.
+#[inline]
+fn ntp_to_f64(secs: u32, frac: u32) -> f64 {
+    secs as f64 + (frac as f64 / (u32::MAX as f64 + 1.0))
+}
+
 /// Default public NTP address.
 pub const POOL_NTP_ADDR: &str = "pool.ntp.org:123";
 
-/// SNTP object which holds the socket handle to obtain timestamp from NTP servers.
-pub struct SntpRequest {
-    socket: UdpSocket,
-    kiss_of_death: Cell<bool>,
+/// A 64-bit fixed-point NTP timestamp (32.32 format): whole seconds since
+/// the NTP epoch (1900-01-01T00:00:00Z) plus a binary fraction of a second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NtpTimestamp {
+    /// Whole seconds since the NTP epoch.
+    pub secs: u32,
+    /// Fractional part of the second, as a 32-bit binary fraction.
+    pub frac: u32,
 }
 
-/// Specialized type for raw time result.
-pub type SntpRawTimeResult = io::Result<u32>;
+impl NtpTimestamp {
+    #[inline]
+    fn parse(bytes: &[u8]) -> NtpTimestamp {
+        NtpTimestamp {
+            secs: read_be_u32(&mut &bytes[0..4]),
+            frac: read_be_u32(&mut &bytes[4..8]),
+        }
+    }
 
-/// Specialized type for Unix time result.
-pub type SntpUnixTimeResult = io::Result<i64>;
+    /// Converts this timestamp into a [`Duration`] since the Unix epoch,
+    /// with the fractional part as its sub-second portion.
+    pub fn as_duration(&self) -> Duration {
+        let nanos = (self.frac as f64 / (u32::MAX as f64 + 1.0)) * 1e9;
+        Duration::new(
+            self.secs.wrapping_sub(SNTP_TIME_OFFSET) as u64,
+            nanos as u32,
+        )
+    }
 
-impl SntpRequest {
-    /// Creates a new SNTP request object.
-    pub fn new() -> SntpRequest {
-        let sntp = SntpRequest {
-            socket: UdpSocket::bind("0.0.0.0:0").unwrap(),
+    /// Converts this timestamp into nanoseconds since the Unix epoch.
+    pub fn as_unix_nanos(&self) -> i64 {
+        self.as_duration().as_nanos() as i64
+    }
+}
+
+/// Clock offset and round-trip delay computed from a full four-timestamp
+/// NTP exchange (T1..T4), as described by the standard SNTP sample
+/// algorithm.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NtpResult {
+    /// Estimated offset, in seconds, between the local clock and the
+    /// server's clock. Add this to the local clock to correct it.
+    pub offset_secs: f64,
+    /// Measured round-trip delay, in seconds, for the exchange.
+    pub delay_secs: f64,
+}
+
+/// Error returned by the trait-based SNTP primitives. Does not depend on
+/// `std`, so it can be used from `#![no_std]` code; the `std` feature adds a
+/// conversion into [`std::io::Error`] for [`SntpRequest`].
+#[derive(Debug)]
+pub enum NtpError {
+    /// The underlying transport failed to send or receive a datagram.
+    Socket,
+    /// The request was not sent whole, or the reply failed validation:
+    /// wrong size, version, mode, or an originate timestamp that does not
+    /// match the nonce we sent.
+    InvalidReply(&'static str),
+}
+
+#[cfg(feature = "std")]
+impl From<NtpError> for Error {
+    fn from(error: NtpError) -> Error {
+        match error {
+            NtpError::Socket => Error::other("SNTP socket error"),
+            NtpError::InvalidReply(message) => Error::new(ErrorKind::InvalidData, message),
+        }
+    }
+}
+
+/// Sends and receives raw SNTP datagrams. Implemented for
+/// [`std::net::UdpSocket`] under the `std` feature; implement it for your
+/// own transport to run this crate on embedded/`no_std` targets.
+pub trait NtpUdpSocket {
+    /// Sends `buf` to `addr`, returning the number of bytes written.
+    fn send_to(&self, buf: &[u8], addr: &str) -> Result<usize, NtpError>;
+
+    /// Receives a datagram into `buf`, returning the number of bytes read.
+    fn recv_from(&self, buf: &mut [u8]) -> Result<usize, NtpError>;
+}
+
+/// Supplies the current local time needed to stamp T1/T4 and the originate
+/// timestamp field, as a 32.32 fixed-point `(secs, frac)` pair (seconds
+/// since 1900-01-01).
+pub trait NtpTimestampGenerator {
+    /// Returns the current local time as an NTP timestamp.
+    fn timestamp(&self) -> (u32, u32);
+}
+
+/// Bundles a [`NtpUdpSocket`] and a [`NtpTimestampGenerator`] so the SNTP
+/// exchange logic can run identically on hosted and `no_std` targets.
+///
+/// Unlike [`SntpRequest`], the anti-spoofing nonce is not generated
+/// internally (a `no_std` target may have no RNG available), so callers
+/// supply one to each call.
+pub struct NtpContext<S, T> {
+    socket: S,
+    timestamp_gen: T,
+    kiss_of_death: Cell<bool>,
+}
+
+impl<S, T> NtpContext<S, T> {
+    /// Creates a new context over the given socket and timestamp generator.
+    pub fn new(socket: S, timestamp_gen: T) -> NtpContext<S, T> {
+        NtpContext {
+            socket,
+            timestamp_gen,
             kiss_of_death: Cell::new(false),
-        };
-        sntp.set_timeout(Duration::from_secs(5)).unwrap();
-        sntp
+        }
     }
 
+    /// If server returns `true`, the user should not send requests to it.
+    pub fn is_kiss_of_death(&self) -> bool {
+        self.kiss_of_death.get()
+    }
+
+    /// Returns a reference to the underlying socket.
+    pub fn socket(&self) -> &S {
+        &self.socket
+    }
+}
+
+impl<S: NtpUdpSocket, T: NtpTimestampGenerator> NtpContext<S, T> {
     #[inline]
-    fn send_packet<A: ToSocketAddrs>(&self, addr: A, packet: &mut [u8]) -> SntpRawTimeResult {
+    fn send_packet(&self, addr: &str, packet: &mut [u8], nonce: &[u8; 8]) -> Result<f64, NtpError> {
         // LI (2 bit) - 3 (not in sync), VN (3 bit) - 4 (version),
         // mode (3 bit) - 3 (client)
         packet[0] = (3 << 6) | (4 << 3) | 3;
-        match self.socket.send_to(&packet, addr) {
-            Ok(sent) => {
-                if sent != SNTP_PACKET_SIZE {
-                    return Err(Error::new(
-                        ErrorKind::InvalidData,
-                        "Invalid SNTP packet size sent",
-                    ));
-                }
-                Ok(sent as u32)
-            }
-            Err(error) => return Err(error),
+        // T1: stamp the originate timestamp with our local send time.
+        let (secs, frac) = self.timestamp_gen.timestamp();
+        packet[24..28].copy_from_slice(&secs.to_be_bytes());
+        packet[28..32].copy_from_slice(&frac.to_be_bytes());
+        // Stamp the transmit timestamp with a random nonce: the server must
+        // echo it back in the reply's originate timestamp, which lets us
+        // detect spoofed or stale replies.
+        packet[40..48].copy_from_slice(nonce);
+        let sent = self.socket.send_to(packet, addr)?;
+        if sent != SNTP_PACKET_SIZE {
+            return Err(NtpError::InvalidReply("Invalid SNTP packet size sent"));
         }
+        Ok(ntp_to_f64(secs, frac))
     }
 
     #[inline]
-    fn recv_packet(&self, packet: &mut [u8]) -> SntpRawTimeResult {
-        match self.socket.recv_from(packet) {
-            Ok((recv, _)) => {
-                if recv != SNTP_PACKET_SIZE {
-                    return Err(Error::new(
-                        ErrorKind::InvalidData,
-                        "Invalid SNTP packet size received",
-                    ));
-                }
-                let hdr = packet[0];
-                if (hdr & 0x38) >> 3 != 4 {
-                    return Err(Error::new(
-                        ErrorKind::Other,
-                        "Server returned wrong SNTP version",
-                    ));
-                }
-                let mode = hdr & 0x7;
-                if mode != 4 && mode != 5 {
-                    return Err(Error::new(ErrorKind::Other, "Not a SNTP server reply"));
-                }
-                self.kiss_of_death.set(packet[1] == 0);
-                Ok(read_be_u32(&mut &packet[40..44]))
-            }
-            Err(error) => return Err(error),
+    fn validate_reply(&self, packet: &[u8], recv: usize, nonce: &[u8; 8]) -> Result<(), NtpError> {
+        if recv != SNTP_PACKET_SIZE {
+            return Err(NtpError::InvalidReply("Invalid SNTP packet size received"));
+        }
+        let hdr = packet[0];
+        if (hdr & 0x38) >> 3 != 4 {
+            return Err(NtpError::InvalidReply("Server returned wrong SNTP version"));
+        }
+        let mode = hdr & 0x7;
+        if mode != 4 && mode != 5 {
+            return Err(NtpError::InvalidReply("Not a SNTP server reply"));
+        }
+        if &packet[24..32] != nonce {
+            return Err(NtpError::InvalidReply(
+                "Originate timestamp does not match the nonce we sent",
+            ));
         }
+        self.kiss_of_death.set(packet[1] == 0);
+        Ok(())
+    }
+
+    #[inline]
+    fn recv_packet(&self, packet: &mut [u8], nonce: &[u8; 8]) -> Result<NtpTimestamp, NtpError> {
+        let recv = self.socket.recv_from(packet)?;
+        self.validate_reply(packet, recv, nonce)?;
+        Ok(NtpTimestamp::parse(&packet[40..48]))
+    }
+
+    #[inline]
+    fn recv_packet_offset(
+        &self,
+        packet: &mut [u8],
+        nonce: &[u8; 8],
+    ) -> Result<(f64, f64), NtpError> {
+        let recv = self.socket.recv_from(packet)?;
+        self.validate_reply(packet, recv, nonce)?;
+        // T2: server's receive timestamp.
+        let t2 = NtpTimestamp::parse(&packet[32..40]);
+        // T3: server's transmit timestamp.
+        let t3 = NtpTimestamp::parse(&packet[40..48]);
+        Ok((ntp_to_f64(t2.secs, t2.frac), ntp_to_f64(t3.secs, t3.frac)))
+    }
+
+    /// Obtains the raw time from a NTP server address. `nonce` must be a
+    /// fresh random value; it is echoed back by the server and checked to
+    /// guard against spoofed or stale replies.
+    pub fn get_raw_time_by_addr(
+        &self,
+        addr: &str,
+        nonce: &[u8; 8],
+    ) -> Result<NtpTimestamp, NtpError> {
+        let mut packet = [0u8; SNTP_PACKET_SIZE];
+        self.send_packet(addr, &mut packet, nonce)?;
+        self.recv_packet(&mut packet, nonce)
+    }
+
+    /// Obtains the clock offset and round-trip delay from a NTP server
+    /// address, using the full four-timestamp exchange (T1..T4) described
+    /// by the standard SNTP sample algorithm:
+    ///
+    /// `offset = ((T2 - T1) + (T3 - T4)) / 2` and
+    /// `delay = (T4 - T1) - (T3 - T2)`.
+    ///
+    /// `nonce` must be a fresh random value; see `get_raw_time_by_addr`.
+    pub fn get_offset_by_addr(
+        &self,
+        addr: &str,
+        nonce: &[u8; 8],
+    ) -> Result<NtpResult, NtpError> {
+        let mut packet = [0u8; SNTP_PACKET_SIZE];
+        let t1 = self.send_packet(addr, &mut packet, nonce)?;
+        let (t2, t3) = self.recv_packet_offset(&mut packet, nonce)?;
+        let (t4_secs, t4_frac) = self.timestamp_gen.timestamp();
+        let t4 = ntp_to_f64(t4_secs, t4_frac);
+        Ok(NtpResult {
+            offset_secs: ((t2 - t1) + (t3 - t4)) / 2.0,
+            delay_secs: (t4 - t1) - (t3 - t2),
+        })
+    }
+}
+
+/// Specialized type for raw time result.
+#[cfg(feature = "std")]
+pub type SntpRawTimeResult = io::Result<NtpTimestamp>;
+
+/// Specialized type for Unix time result.
+#[cfg(feature = "std")]
+pub type SntpUnixTimeResult = io::Result<i64>;
+
+/// Specialized type for clock offset/round-trip delay result.
+#[cfg(feature = "std")]
+pub type SntpOffsetResult = io::Result<NtpResult>;
+
+#[cfg(feature = "std")]
+impl NtpUdpSocket for UdpSocket {
+    fn send_to(&self, buf: &[u8], addr: &str) -> Result<usize, NtpError> {
+        UdpSocket::send_to(self, buf, addr).map_err(|_| NtpError::Socket)
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> Result<usize, NtpError> {
+        UdpSocket::recv_from(self, buf)
+            .map(|(recv, _)| recv)
+            .map_err(|_| NtpError::Socket)
+    }
+}
+
+/// [`NtpTimestampGenerator`] backed by [`std::time::SystemTime`].
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StdTimestampGenerator;
+
+#[cfg(feature = "std")]
+impl NtpTimestampGenerator for StdTimestampGenerator {
+    fn timestamp(&self) -> (u32, u32) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let secs = now.as_secs() as u32 + SNTP_TIME_OFFSET;
+        let frac = ((now.subsec_nanos() as f64 / 1e9) * (u32::MAX as f64 + 1.0)) as u32;
+        (secs, frac)
+    }
+}
+
+/// Generates a fresh cryptographically random nonce to stamp the outgoing
+/// packet's transmit timestamp, so the matching reply can be authenticated.
+#[cfg(feature = "std")]
+#[inline]
+fn random_nonce() -> [u8; 8] {
+    let mut nonce = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    nonce
+}
+
+#[cfg(feature = "std")]
+#[inline]
+fn resolve_addr<A: ToSocketAddrs>(addr: A) -> io::Result<String> {
+    let addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
+        Error::new(
+            ErrorKind::AddrNotAvailable,
+            "Could not resolve NTP server address",
+        )
+    })?;
+    Ok(addr.to_string())
+}
+
+/// SNTP object which holds the socket handle to obtain timestamp from NTP servers.
+#[cfg(feature = "std")]
+pub struct SntpRequest {
+    context: NtpContext<UdpSocket, StdTimestampGenerator>,
+}
+
+#[cfg(feature = "std")]
+impl Default for SntpRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl SntpRequest {
+    /// Creates a new SNTP request object.
+    pub fn new() -> SntpRequest {
+        let sntp = SntpRequest {
+            context: NtpContext::new(
+                UdpSocket::bind("0.0.0.0:0").unwrap(),
+                StdTimestampGenerator,
+            ),
+        };
+        sntp.set_timeout(Duration::from_secs(5)).unwrap();
+        sntp
     }
 
     /// If server returns `true`, the user should not send requests to it.
     pub fn is_kiss_of_death(&self) -> bool {
-        self.kiss_of_death.get()
+        self.context.is_kiss_of_death()
     }
 
     /// Sets the inactivity time to the client get time out. If not specified, the client assumes 5 seconds as default.
     pub fn set_timeout(&self, timeout: Duration) -> io::Result<()> {
         let dur = Some(timeout);
-        self.socket.set_write_timeout(dur)?;
-        self.socket.set_read_timeout(dur)
+        self.context.socket().set_write_timeout(dur)?;
+        self.context.socket().set_read_timeout(dur)
     }
 
     /// Obtains the raw time from a NTP server address.
     pub fn get_raw_time_by_addr<A: ToSocketAddrs>(&self, addr: A) -> SntpRawTimeResult {
-        let mut packet = [0u8; SNTP_PACKET_SIZE];
-        self.send_packet(addr, &mut packet)?;
-        self.recv_packet(&mut packet)
+        let addr = resolve_addr(addr)?;
+        let nonce = random_nonce();
+        Ok(self.context.get_raw_time_by_addr(&addr, &nonce)?)
     }
 
     /// Obtains the [Unix time](https://en.wikipedia.org/wiki/Unix_time) from a NTP server address.
     pub fn get_unix_time_by_addr<A: ToSocketAddrs>(&self, addr: A) -> SntpUnixTimeResult {
         let raw_time = self.get_raw_time_by_addr(addr)?;
-        Ok((raw_time - SNTP_TIME_OFFSET) as i64)
+        Ok((raw_time.secs.wrapping_sub(SNTP_TIME_OFFSET)) as i64)
     }
 
     /// Obtains the raw time from default NTP server address [`POOL_NTP_ADDR`](constant.POOL_NTP_ADDR.html).
@@ -143,4 +420,252 @@ impl SntpRequest {
     pub fn get_unix_time(&self) -> SntpUnixTimeResult {
         self.get_unix_time_by_addr(POOL_NTP_ADDR)
     }
+
+    /// Obtains the clock offset and round-trip delay from a NTP server
+    /// address, using the full four-timestamp exchange (T1..T4). See
+    /// `NtpContext::get_offset_by_addr` for the algorithm.
+    pub fn get_offset_by_addr<A: ToSocketAddrs>(&self, addr: A) -> SntpOffsetResult {
+        let addr = resolve_addr(addr)?;
+        let nonce = random_nonce();
+        Ok(self.context.get_offset_by_addr(&addr, &nonce)?)
+    }
+
+    /// Obtains the clock offset and round-trip delay from default NTP server
+    /// address [`POOL_NTP_ADDR`](constant.POOL_NTP_ADDR.html).
+    pub fn get_offset(&self) -> SntpOffsetResult {
+        self.get_offset_by_addr(POOL_NTP_ADDR)
+    }
+
+    /// Queries several NTP server addresses and returns the most trustworthy
+    /// offset/delay sample among them.
+    ///
+    /// Each address is queried with [`get_offset_by_addr`](SntpRequest::get_offset_by_addr).
+    /// Samples that fail, are flagged kiss-of-death, or have a non-positive
+    /// or implausibly large round-trip delay are discarded; among the
+    /// survivors, the one with the smallest round-trip delay is returned,
+    /// since delay is the best available proxy for accuracy. This is more
+    /// resistant to a single bad or lying server than trusting one address.
+    pub fn get_best_offset(&self, addrs: &[&str]) -> SntpOffsetResult {
+        let mut best: Option<NtpResult> = None;
+        for addr in addrs {
+            let result = match self.get_offset_by_addr(addr) {
+                Ok(result) => result,
+                Err(_) => continue,
+            };
+            if self.is_kiss_of_death() || !is_plausible_delay(result.delay_secs) {
+                continue;
+            }
+            if best.is_none_or(|current| result.delay_secs < current.delay_secs) {
+                best = Some(result);
+            }
+        }
+        best.ok_or_else(|| Error::new(ErrorKind::TimedOut, "No usable SNTP server responded"))
+    }
+
+    /// Queries several NTP server addresses and returns the median offset
+    /// across the surviving samples, using the same filtering as
+    /// [`get_best_offset`](SntpRequest::get_best_offset).
+    ///
+    /// The median is less sensitive to a single outlier server than the
+    /// smallest-delay sample, at the cost of needing more servers to be
+    /// reachable.
+    pub fn get_median_offset(&self, addrs: &[&str]) -> SntpOffsetResult {
+        let mut samples: Vec<NtpResult> = Vec::new();
+        for addr in addrs {
+            let result = match self.get_offset_by_addr(addr) {
+                Ok(result) => result,
+                Err(_) => continue,
+            };
+            if self.is_kiss_of_death() || !is_plausible_delay(result.delay_secs) {
+                continue;
+            }
+            samples.push(result);
+        }
+        if samples.is_empty() {
+            return Err(Error::new(
+                ErrorKind::TimedOut,
+                "No usable SNTP server responded",
+            ));
+        }
+        samples.sort_by(|a, b| a.offset_secs.partial_cmp(&b.offset_secs).unwrap());
+        let mid = samples.len() / 2;
+        // `delay_secs` tracks whichever sample(s) `offset_secs` is derived
+        // from above, rather than an independent median of all delays: it is
+        // informational only, since the median offset is not tied to any
+        // single real measurement.
+        let (offset_secs, delay_secs) = if samples.len().is_multiple_of(2) {
+            (
+                (samples[mid - 1].offset_secs + samples[mid].offset_secs) / 2.0,
+                (samples[mid - 1].delay_secs + samples[mid].delay_secs) / 2.0,
+            )
+        } else {
+            (samples[mid].offset_secs, samples[mid].delay_secs)
+        };
+        Ok(NtpResult {
+            offset_secs,
+            delay_secs,
+        })
+    }
+}
+
+/// Largest round-trip delay, in seconds, still considered plausible when
+/// selecting a sample in [`SntpRequest::get_best_offset`] and
+/// [`SntpRequest::get_median_offset`].
+#[cfg(feature = "std")]
+const MAX_PLAUSIBLE_DELAY_SECS: f64 = 10.0;
+
+#[cfg(feature = "std")]
+#[inline]
+fn is_plausible_delay(delay_secs: f64) -> bool {
+    delay_secs > 0.0 && delay_secs <= MAX_PLAUSIBLE_DELAY_SECS
+}
+
+/// Minimal SNTP server that answers client requests, mirroring whatever
+/// clock `T` reports. Useful as a time source on local test networks or
+/// embedded fleets that cannot reach the public NTP pool.
+#[cfg(feature = "std")]
+pub struct SntpServer<T: NtpTimestampGenerator + Send + Sync + 'static> {
+    socket: UdpSocket,
+    timestamp_gen: Arc<T>,
+    stratum: u8,
+}
+
+#[cfg(feature = "std")]
+impl<T: NtpTimestampGenerator + Send + Sync + 'static> SntpServer<T> {
+    /// Binds a new SNTP server to `addr`, answering with the given
+    /// `stratum` and mirroring whatever time `timestamp_gen` reports.
+    pub fn new<A: ToSocketAddrs>(addr: A, stratum: u8, timestamp_gen: T) -> io::Result<SntpServer<T>> {
+        Ok(SntpServer {
+            socket: UdpSocket::bind(addr)?,
+            timestamp_gen: Arc::new(timestamp_gen),
+            stratum,
+        })
+    }
+
+    /// Returns the local address this server is bound to, e.g. to discover
+    /// the ephemeral port chosen when binding to port `0`.
+    pub fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Serves requests on the calling thread until a socket error occurs.
+    pub fn serve(&self) -> io::Result<()> {
+        Self::serve_on(&self.socket, &*self.timestamp_gen, self.stratum)
+    }
+
+    /// Spawns `threads` additional reader threads, each serving requests
+    /// from a clone of the underlying socket, for higher throughput.
+    pub fn serve_multi_threaded(&self, threads: usize) -> io::Result<Vec<thread::JoinHandle<io::Result<()>>>> {
+        let mut handles = Vec::with_capacity(threads);
+        for _ in 0..threads {
+            let socket = self.socket.try_clone()?;
+            let timestamp_gen = Arc::clone(&self.timestamp_gen);
+            let stratum = self.stratum;
+            handles.push(thread::spawn(move || {
+                Self::serve_on(&socket, &*timestamp_gen, stratum)
+            }));
+        }
+        Ok(handles)
+    }
+
+    fn serve_on(socket: &UdpSocket, timestamp_gen: &T, stratum: u8) -> io::Result<()> {
+        let mut packet = [0u8; SNTP_PACKET_SIZE];
+        loop {
+            let (recv, client) = socket.recv_from(&mut packet)?;
+            let receive_ts = timestamp_gen.timestamp();
+            if let Some(reply) = build_reply(&packet[..recv], receive_ts, stratum, timestamp_gen) {
+                socket.send_to(&reply, client)?;
+            }
+        }
+    }
+}
+
+/// Builds a 48-byte SNTP reply for `request`, or `None` if `request` is not
+/// a valid mode-3 client packet.
+#[cfg(feature = "std")]
+fn build_reply<T: NtpTimestampGenerator>(
+    request: &[u8],
+    receive_ts: (u32, u32),
+    stratum: u8,
+    timestamp_gen: &T,
+) -> Option<[u8; SNTP_PACKET_SIZE]> {
+    if request.len() != SNTP_PACKET_SIZE {
+        return None;
+    }
+    let hdr = request[0];
+    if (hdr & 0x38) >> 3 != 4 || hdr & 0x7 != 3 {
+        return None;
+    }
+
+    let mut reply = [0u8; SNTP_PACKET_SIZE];
+    // LI (2 bit) - 0 (no warning), VN (3 bit) - 4 (version), mode (3 bit) - 4 (server)
+    reply[0] = (4 << 3) | 4;
+    reply[1] = stratum;
+    reply[2] = request[2]; // mirror the client's poll interval
+    reply[3] = request[3]; // mirror the client's precision
+
+    let (ref_secs, ref_frac) = timestamp_gen.timestamp();
+    reply[16..20].copy_from_slice(&ref_secs.to_be_bytes());
+    reply[20..24].copy_from_slice(&ref_frac.to_be_bytes());
+
+    // Originate timestamp: echo back the client's transmit timestamp.
+    reply[24..32].copy_from_slice(&request[40..48]);
+
+    // Receive timestamp: when we received the client's request.
+    reply[32..36].copy_from_slice(&receive_ts.0.to_be_bytes());
+    reply[36..40].copy_from_slice(&receive_ts.1.to_be_bytes());
+
+    // Transmit timestamp: stamped right before sending.
+    let (tx_secs, tx_frac) = timestamp_gen.timestamp();
+    reply[40..44].copy_from_slice(&tx_secs.to_be_bytes());
+    reply[44..48].copy_from_slice(&tx_frac.to_be_bytes());
+
+    Some(reply)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    struct FixedClock(u32, u32);
+
+    impl NtpTimestampGenerator for FixedClock {
+        fn timestamp(&self) -> (u32, u32) {
+            (self.0, self.1)
+        }
+    }
+
+    #[test]
+    fn build_reply_rejects_malformed_requests() {
+        let clock = FixedClock(100, 0);
+        // Wrong size.
+        assert!(build_reply(&[0u8; 10], (0, 0), 1, &clock).is_none());
+        // Wrong version (VN 3 instead of 4), valid client mode.
+        let mut bad_version = [0u8; SNTP_PACKET_SIZE];
+        bad_version[0] = (3 << 3) | 3;
+        assert!(build_reply(&bad_version, (0, 0), 1, &clock).is_none());
+        // Valid version, wrong mode (4 instead of client mode 3).
+        let mut bad_mode = [0u8; SNTP_PACKET_SIZE];
+        bad_mode[0] = (4 << 3) | 4;
+        assert!(build_reply(&bad_mode, (0, 0), 1, &clock).is_none());
+    }
+
+    #[test]
+    fn build_reply_mirrors_client_and_stamps_timestamps() {
+        let clock = FixedClock(1_000, 2_000);
+        let mut request = [0u8; SNTP_PACKET_SIZE];
+        request[0] = (4 << 3) | 3; // VN 4, mode 3 (client)
+        let nonce = [1, 2, 3, 4, 5, 6, 7, 8];
+        request[40..48].copy_from_slice(&nonce);
+
+        let reply = build_reply(&request, (500, 600), 2, &clock).unwrap();
+
+        assert_eq!(reply[0], (4 << 3) | 4); // LI 0, VN 4, mode 4 (server)
+        assert_eq!(reply[1], 2); // stratum
+        assert_eq!(&reply[24..32], &nonce); // originate <- client's transmit (nonce)
+        assert_eq!(&reply[32..36], &500u32.to_be_bytes()); // receive secs
+        assert_eq!(&reply[36..40], &600u32.to_be_bytes()); // receive frac
+        assert_eq!(&reply[40..44], &1_000u32.to_be_bytes()); // transmit secs
+        assert_eq!(&reply[44..48], &2_000u32.to_be_bytes()); // transmit frac
+    }
 }
@@ -1,9 +1,11 @@
 extern crate sntp_request;
 
+use std::net::UdpSocket;
+use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use sntp_request::SntpRequest;
+use sntp_request::{SntpRequest, SntpServer, StdTimestampGenerator};
 
 #[test]
 fn get_unix_time() {
@@ -14,3 +16,189 @@ fn get_unix_time() {
     let t2 = sntp.get_unix_time().unwrap();
     assert!(t2 > t1);
 }
+
+#[test]
+fn sntp_server_answers_sntp_request_over_loopback() {
+    let server = Arc::new(SntpServer::new("127.0.0.1:0", 2, StdTimestampGenerator).unwrap());
+    let addr = server.local_addr().unwrap();
+    let server_loop = Arc::clone(&server);
+    thread::spawn(move || {
+        let _ = server_loop.serve();
+    });
+
+    let sntp = SntpRequest::new();
+    sntp.set_timeout(Duration::from_secs(5)).unwrap();
+    let timestamp = sntp.get_raw_time_by_addr(addr).unwrap();
+    assert!(timestamp.secs > 0);
+    assert!(!sntp.is_kiss_of_death());
+}
+
+// NTP epoch (1900-01-01) to Unix epoch offset, in seconds.
+const SNTP_TIME_OFFSET: u32 = 2_208_988_800;
+
+/// Crafts a 48-byte SNTP reply as if sent by a server whose clock is
+/// `delta_secs` away from ours, mirroring the client's nonce into the
+/// originate field the way a real server would.
+fn reply_with_offset(request: &[u8], delta_secs: f64) -> [u8; 48] {
+    let mut reply = [0u8; 48];
+    reply[0] = (4 << 3) | 4; // LI 0, VN 4, mode 4 (server)
+    reply[1] = 1; // stratum
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let base =
+        (now.as_secs() as u32 + SNTP_TIME_OFFSET) as f64 + (now.subsec_nanos() as f64 / 1e9);
+    let adjusted = base + delta_secs;
+    let secs = adjusted.floor() as u32;
+    let frac = ((adjusted - adjusted.floor()) * (u32::MAX as f64 + 1.0)) as u32;
+
+    // Receive and transmit timestamp are the same: the fake server answers
+    // instantly, so it doesn't contribute any processing delay of its own.
+    reply[32..36].copy_from_slice(&secs.to_be_bytes());
+    reply[36..40].copy_from_slice(&frac.to_be_bytes());
+    reply[40..44].copy_from_slice(&secs.to_be_bytes());
+    reply[44..48].copy_from_slice(&frac.to_be_bytes());
+
+    // Originate timestamp: echo back the client's nonce.
+    reply[24..32].copy_from_slice(&request[40..48]);
+    reply
+}
+
+#[test]
+fn get_median_offset_combines_samples_and_keeps_delay_coherent() {
+    let deltas = [-0.3_f64, -0.1, 0.1, 0.3];
+    let mut addrs = Vec::new();
+    let mut handles = Vec::new();
+    for &delta in deltas.iter() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        addrs.push(socket.local_addr().unwrap().to_string());
+        handles.push(thread::spawn(move || {
+            let mut buf = [0u8; 48];
+            let (_, client) = socket.recv_from(&mut buf).unwrap();
+            let reply = reply_with_offset(&buf, delta);
+            socket.send_to(&reply, client).unwrap();
+        }));
+    }
+
+    let sntp = SntpRequest::new();
+    sntp.set_timeout(Duration::from_secs(5)).unwrap();
+    let addr_refs: Vec<&str> = addrs.iter().map(String::as_str).collect();
+    let result = sntp.get_median_offset(&addr_refs).unwrap();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    // Sorted deltas are -0.3, -0.1, 0.1, 0.3: with 4 samples the median
+    // offset averages the two middle ones, which cancel out to ~0.
+    assert!(
+        result.offset_secs.abs() < 0.05,
+        "offset_secs: {}",
+        result.offset_secs
+    );
+    // delay_secs must track the same two middle-by-offset samples the
+    // offset was computed from, so it stays a small, plausible round-trip
+    // delay rather than an arbitrary leftover from the sort.
+    assert!(
+        result.delay_secs > 0.0 && result.delay_secs < 1.0,
+        "delay_secs: {}",
+        result.delay_secs
+    );
+}
+
+#[test]
+fn get_offset_by_addr_rejects_reply_with_wrong_nonce() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap().to_string();
+    let handle = thread::spawn(move || {
+        let mut buf = [0u8; 48];
+        let (_, client) = socket.recv_from(&mut buf).unwrap();
+        // Ignore the client's actual nonce and echo back a zeroed originate
+        // timestamp instead: a spoofed/stale reply must not be accepted.
+        let reply = reply_with_offset(&[0u8; 48], 0.0);
+        socket.send_to(&reply, client).unwrap();
+    });
+
+    let sntp = SntpRequest::new();
+    sntp.set_timeout(Duration::from_secs(5)).unwrap();
+    let err = sntp.get_offset_by_addr(addr.as_str()).unwrap_err();
+    handle.join().unwrap();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+/// Crafts a reply like [`reply_with_offset`], but lets the caller also
+/// control the round-trip delay the client will observe (via an offset
+/// between the receive and transmit timestamps, rather than an actual
+/// sleep) and the stratum (`0` signals kiss-of-death).
+fn reply_with_offset_and_delay(
+    request: &[u8],
+    delta_secs: f64,
+    delay_bias_secs: f64,
+    stratum: u8,
+) -> [u8; 48] {
+    let mut reply = [0u8; 48];
+    reply[0] = (4 << 3) | 4; // LI 0, VN 4, mode 4 (server)
+    reply[1] = stratum;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let base = (now.as_secs() as u32 + SNTP_TIME_OFFSET) as f64
+        + (now.subsec_nanos() as f64 / 1e9)
+        + delta_secs;
+    let encode = |t: f64| {
+        let secs = t.floor() as u32;
+        let frac = ((t - t.floor()) * (u32::MAX as f64 + 1.0)) as u32;
+        (secs, frac)
+    };
+    let (rx_secs, rx_frac) = encode(base);
+    let (tx_secs, tx_frac) = encode(base - delay_bias_secs);
+    reply[32..36].copy_from_slice(&rx_secs.to_be_bytes());
+    reply[36..40].copy_from_slice(&rx_frac.to_be_bytes());
+    reply[40..44].copy_from_slice(&tx_secs.to_be_bytes());
+    reply[44..48].copy_from_slice(&tx_frac.to_be_bytes());
+
+    reply[24..32].copy_from_slice(&request[40..48]);
+    reply
+}
+
+#[test]
+fn get_best_offset_picks_smallest_delay_survivor() {
+    // (offset delta, extra round-trip delay, stratum)
+    let servers = [
+        (0.2_f64, 0.0_f64, 1u8), // good, fast: should win
+        (-0.2, 2.0, 1),          // good, but slower: plausible, loses
+        (0.0, 15.0, 1),          // implausible delay (> 10s): filtered out
+        (0.0, 0.0, 0),           // kiss-of-death (stratum 0): filtered out
+    ];
+    let mut addrs = Vec::new();
+    let mut handles = Vec::new();
+    for &(delta, delay_bias, stratum) in servers.iter() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        addrs.push(socket.local_addr().unwrap().to_string());
+        handles.push(thread::spawn(move || {
+            let mut buf = [0u8; 48];
+            let (_, client) = socket.recv_from(&mut buf).unwrap();
+            let reply = reply_with_offset_and_delay(&buf, delta, delay_bias, stratum);
+            socket.send_to(&reply, client).unwrap();
+        }));
+    }
+
+    let sntp = SntpRequest::new();
+    sntp.set_timeout(Duration::from_secs(5)).unwrap();
+    let addr_refs: Vec<&str> = addrs.iter().map(String::as_str).collect();
+    let result = sntp.get_best_offset(&addr_refs).unwrap();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    // Only the first two servers are both plausible-delay and not
+    // kiss-of-death; the first has the smaller delay, so its offset wins.
+    assert!(
+        (result.offset_secs - 0.2).abs() < 0.05,
+        "offset_secs: {}",
+        result.offset_secs
+    );
+    assert!(
+        result.delay_secs > 0.0 && result.delay_secs < 1.0,
+        "delay_secs: {}",
+        result.delay_secs
+    );
+}